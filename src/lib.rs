@@ -2,23 +2,107 @@
 use anyhow::Result;
 use once_cell::sync::Lazy;
 use std::{
-    fmt,
+    cell::RefCell,
+    env, fmt,
     fs::{File, OpenOptions},
-    io::Write,
-    path::Path,
-    sync::Mutex,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
 const DEFAULT_FILENAME: &str = "./.yash.log";
 
+/// Errors returned by the logging entry points. Keeping misconfiguration
+/// recoverable lets the crate be embedded without risking a runtime panic.
+#[derive(Debug, thiserror::Error)]
+pub enum LoggeryError {
+    /// A record was logged while no output drain is configured: the default
+    /// `./.yash.log` file could not be opened and `init_loggery` has not set
+    /// up another drain.
+    #[error("loggery has not been initialized")]
+    NotInitialized,
+    /// Writing the record to its drain failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// What to do when the target log file already exists.
 #[derive(Debug, Clone, Copy)]
-enum LogLevel {
+pub enum IfExists {
+    /// Keep existing contents and append new records (the historical behavior).
+    Append,
+    /// Truncate the file so each run starts fresh.
+    Truncate,
+    /// Refuse to open an existing file.
+    Fail,
+}
+
+/// Serialization format for each record.
+#[derive(Debug, Clone, Copy)]
+pub enum LogFormat {
+    /// `[LEVEL] message` plain text (the default).
+    Plain,
+    /// Newline-delimited JSON objects, as dropshot's file drain emits.
+    Json,
+}
+
+/// Where a `Logger` sends its records. Modeled on dropshot's `ConfigLogging`.
+#[derive(Debug, Clone)]
+pub enum ConfigLogging {
+    /// Emit to stderr, with ANSI-colored level tags when it is a terminal.
+    StderrTerminal,
+    /// Append or truncate a log file on disk.
+    File { path: PathBuf, if_exists: IfExists },
+    /// Write to both a file and the stderr terminal.
+    Both { path: PathBuf, if_exists: IfExists },
+    /// Ship records to the Unix system logger (`/dev/log`). A no-op on targets
+    /// without a syslog socket.
+    Syslog,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LogLevel {
     Error,
     Warn,
     Info,
     Todo,
 }
 
+impl LogLevel {
+    /// Ordered severity used for level filtering: `Error = 0`, `Warn = 1`,
+    /// `Info = 2`. A message is emitted when its severity is `<=` the logger's
+    /// `max_level` severity, so a lower number means a higher priority. `Todo`
+    /// shares the top priority (`0`) so it is never filtered out.
+    fn severity(&self) -> u8 {
+        match self {
+            Self::Error => 0,
+            Self::Warn => 1,
+            Self::Info => 2,
+            Self::Todo => 0,
+        }
+    }
+
+    /// Lowercase level name used in JSON records.
+    fn as_lowercase(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Todo => "todo",
+        }
+    }
+
+    /// Parse a `RUST_LOG`-style level name, case-insensitively.
+    fn from_env_str(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for LogLevel {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -30,50 +114,287 @@ impl fmt::Display for LogLevel {
     }
 }
 
+/// A structured field value: either a rendered leaf or a named nested group.
+pub enum StructuredValue {
+    Leaf(String),
+    Nested(Vec<(String, StructuredValue)>),
+}
+
 static LOGGER: Lazy<Mutex<Logger>> = Lazy::new(|| Mutex::new(Logger::default()));
 
+thread_local! {
+    /// When set, records for the current thread are collected into this buffer
+    /// instead of being written to the file or terminal. Thread-local so
+    /// capturing tests can run concurrently without interfering.
+    static CAPTURE: RefCell<Option<Arc<Mutex<Vec<String>>>>> = const { RefCell::new(None) };
+}
+
 struct Logger {
     file: Option<File>,
+    terminal: bool,
+    max_level: LogLevel,
+    format: LogFormat,
+    timestamp: bool,
+    /// Whether a syslog drain was requested. Tracked on every target so that a
+    /// selected-but-unavailable socket (no daemon, or a non-unix build) is a
+    /// silent no-op instead of a `NotInitialized` error.
+    syslog_selected: bool,
+    #[cfg(unix)]
+    syslog: Option<std::os::unix::net::UnixDatagram>,
 }
 
 impl Logger {
     fn new() -> Self {
-        Self { file: None }
+        Self {
+            file: None,
+            terminal: false,
+            max_level: LogLevel::Info,
+            format: LogFormat::Plain,
+            timestamp: false,
+            syslog_selected: false,
+            #[cfg(unix)]
+            syslog: None,
+        }
     }
 
-    fn init(&mut self, filename: impl AsRef<Path>) -> Result<&mut Self> {
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(filename)?;
-        self.file = Some(file);
+    /// Connect to the system log socket, returning `None` (silent fallback) if
+    /// no syslog daemon is listening.
+    #[cfg(unix)]
+    fn connect_syslog() -> Option<std::os::unix::net::UnixDatagram> {
+        let socket = std::os::unix::net::UnixDatagram::unbound().ok()?;
+        for path in ["/dev/log", "/var/run/syslog"] {
+            if socket.connect(path).is_ok() {
+                return Some(socket);
+            }
+        }
+        None
+    }
+
+    /// Map a [`LogLevel`] onto an RFC 3164 syslog severity.
+    #[cfg(unix)]
+    fn syslog_severity(level: LogLevel) -> u8 {
+        match level {
+            LogLevel::Error => 3,   // LOG_ERR
+            LogLevel::Warn => 4,    // LOG_WARNING
+            LogLevel::Info => 6,    // LOG_INFO
+            LogLevel::Todo => 7,    // LOG_DEBUG
+        }
+    }
+
+    /// Render the current RFC 3339 UTC timestamp, e.g. `2024-05-01T12:00:00.123Z`.
+    fn now() -> String {
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+    }
+
+    fn init(&mut self, config: ConfigLogging) -> Result<&mut Self, LoggeryError> {
+        match config {
+            ConfigLogging::StderrTerminal => {
+                self.file = None;
+                self.terminal = true;
+            }
+            ConfigLogging::File { path, if_exists } => {
+                self.file = Some(Self::open_file(path, if_exists)?);
+                self.terminal = false;
+            }
+            ConfigLogging::Both { path, if_exists } => {
+                self.file = Some(Self::open_file(path, if_exists)?);
+                self.terminal = true;
+            }
+            ConfigLogging::Syslog => {
+                self.file = None;
+                self.terminal = false;
+                self.syslog_selected = true;
+                #[cfg(unix)]
+                {
+                    self.syslog = Self::connect_syslog();
+                }
+            }
+        }
         Ok(self)
     }
 
-    fn log_content(&self, log_level: LogLevel, message: &str) -> Result<()> {
-        let mut file = self.file.as_ref().unwrap();
-        writeln!(file, "{} {}", log_level, message)?;
-        file.flush()?;
+    fn open_file(path: impl AsRef<Path>, if_exists: IfExists) -> Result<File, LoggeryError> {
+        let mut options = OpenOptions::new();
+        options.write(true);
+        match if_exists {
+            IfExists::Append => {
+                options.create(true).append(true);
+            }
+            IfExists::Truncate => {
+                options.create(true).truncate(true);
+            }
+            IfExists::Fail => {
+                options.create_new(true);
+            }
+        }
+        Ok(options.open(path)?)
+    }
+
+    /// Render a level tag, optionally wrapped in its ANSI color for terminals.
+    fn level_tag(level: LogLevel, color: bool) -> String {
+        if !color {
+            return format!("{}", level);
+        }
+        let code = match level {
+            LogLevel::Error => "31",
+            LogLevel::Warn => "33",
+            LogLevel::Info => "32",
+            LogLevel::Todo => "36",
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, level)
+    }
+
+    fn log_content(&self, log_level: LogLevel, message: &str) -> Result<(), LoggeryError> {
+        if log_level.severity() > self.max_level.severity() {
+            return Ok(());
+        }
+        let timestamp = if self.timestamp {
+            Some(Self::now())
+        } else {
+            None
+        };
+        let captured = CAPTURE.with(|slot| {
+            if let Some(buffer) = slot.borrow().as_ref() {
+                let prefix = timestamp
+                    .as_deref()
+                    .map(|t| format!("{} ", t))
+                    .unwrap_or_default();
+                buffer
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}{} {}", prefix, log_level, message));
+                true
+            } else {
+                false
+            }
+        });
+        if captured {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        let has_syslog = self.syslog.is_some();
+        #[cfg(not(unix))]
+        let has_syslog = false;
+        if self.file.is_none() && !self.terminal && !has_syslog {
+            // A syslog drain was requested but no socket is available (no daemon
+            // listening, or a non-unix target). Fall back silently rather than
+            // erroring on every record.
+            if self.syslog_selected {
+                return Ok(());
+            }
+            return Err(LoggeryError::NotInitialized);
+        }
+        match self.format {
+            LogFormat::Plain => {
+                let prefix = timestamp
+                    .as_deref()
+                    .map(|t| format!("{} ", t))
+                    .unwrap_or_default();
+                if let Some(mut file) = self.file.as_ref() {
+                    writeln!(file, "{}{} {}", prefix, log_level, message)?;
+                    file.flush()?;
+                }
+                if self.terminal {
+                    let mut stderr = std::io::stderr();
+                    let color = stderr.is_terminal();
+                    writeln!(
+                        stderr,
+                        "{}{} {}",
+                        prefix,
+                        Self::level_tag(log_level, color),
+                        message
+                    )?;
+                    stderr.flush()?;
+                }
+            }
+            LogFormat::Json => {
+                // Build the line in explicit Bunyan order (`time`, `level`,
+                // `msg`). `serde_json::Map` would sort keys alphabetically
+                // unless the `preserve_order` feature is on, so emit the object
+                // field by field with each value individually JSON-escaped.
+                let level =
+                    serde_json::Value::String(log_level.as_lowercase().to_string()).to_string();
+                let msg = serde_json::Value::String(message.to_string()).to_string();
+                let line = match timestamp {
+                    Some(ref t) => {
+                        let time = serde_json::Value::String(t.clone()).to_string();
+                        format!(
+                            "{{\"time\":{},\"level\":{},\"msg\":{}}}",
+                            time, level, msg
+                        )
+                    }
+                    None => format!("{{\"level\":{},\"msg\":{}}}", level, msg),
+                };
+                if let Some(mut file) = self.file.as_ref() {
+                    writeln!(file, "{}", line)?;
+                    file.flush()?;
+                }
+                if self.terminal {
+                    let mut stderr = std::io::stderr();
+                    writeln!(stderr, "{}", line)?;
+                    stderr.flush()?;
+                }
+            }
+        }
+        #[cfg(unix)]
+        if let Some(ref socket) = self.syslog {
+            let priority = 8 + Self::syslog_severity(log_level); // facility LOG_USER
+            let datagram = format!("<{}>{} {}", priority, log_level, message);
+            // Silent fallback: never panic if the daemon went away.
+            let _ = socket.send(datagram.as_bytes());
+        }
         Ok(())
     }
 
-    fn info(&self, message: &str) -> Result<()> {
+    /// Log `message` followed by an indented block of structured fields. Each
+    /// leaf is rendered as `<indent>key: value`; a named group prints a header
+    /// line and recurses two spaces deeper.
+    fn log_structured(
+        &self,
+        level: LogLevel,
+        message: &str,
+        fields: Vec<(String, StructuredValue)>,
+    ) -> Result<(), LoggeryError> {
+        let mut block = String::new();
+        Self::write_fields(&mut block, &fields, 2);
+        let body = if block.is_empty() {
+            message.to_string()
+        } else {
+            format!("{}\n{}", message, block.trim_end_matches('\n'))
+        };
+        self.log_content(level, &body)
+    }
+
+    fn write_fields(buf: &mut String, fields: &[(String, StructuredValue)], indent: usize) {
+        for (key, value) in fields {
+            match value {
+                StructuredValue::Leaf(rendered) => {
+                    buf.push_str(&format!("{:indent$}{}: {}\n", "", key, rendered));
+                }
+                StructuredValue::Nested(inner) => {
+                    buf.push_str(&format!("{:indent$}{}:\n", "", key));
+                    Self::write_fields(buf, inner, indent + 2);
+                }
+            }
+        }
+    }
+
+    fn info(&self, message: &str) -> Result<(), LoggeryError> {
         self.log_content(LogLevel::Info, message)?;
         Ok(())
     }
 
-    fn warn(&self, message: &str) -> Result<()> {
+    fn warn(&self, message: &str) -> Result<(), LoggeryError> {
         self.log_content(LogLevel::Warn, message)?;
         Ok(())
     }
 
-    fn error(&self, message: &str) -> Result<()> {
+    fn error(&self, message: &str) -> Result<(), LoggeryError> {
         self.log_content(LogLevel::Error, message)?;
         Ok(())
     }
 
-    fn todo(&self, message: &str) -> Result<()> {
+    fn todo(&self, message: &str) -> Result<(), LoggeryError> {
         self.log_content(LogLevel::Todo, message)?;
         Ok(())
     }
@@ -85,6 +406,15 @@ impl Clone for Logger {
         if let Some(ref file) = self.file {
             instance.file = file.try_clone().ok();
         }
+        instance.terminal = self.terminal;
+        instance.max_level = self.max_level;
+        instance.format = self.format;
+        instance.timestamp = self.timestamp;
+        instance.syslog_selected = self.syslog_selected;
+        #[cfg(unix)]
+        {
+            instance.syslog = self.syslog.as_ref().and_then(|s| s.try_clone().ok());
+        }
         instance
     }
 }
@@ -92,24 +422,68 @@ impl Clone for Logger {
 impl Default for Logger {
     fn default() -> Self {
         let mut instance = Self::new();
-        let instance = instance.init(DEFAULT_FILENAME).unwrap();
-        instance.clone()
+        // Best-effort default target; if it cannot be opened the logger stays
+        // uninitialized and logging returns `LoggeryError::NotInitialized`
+        // rather than panicking.
+        let _ = instance.init(ConfigLogging::File {
+            path: PathBuf::from(DEFAULT_FILENAME),
+            if_exists: IfExists::Append,
+        });
+        instance
     }
 }
 
 impl Drop for Logger {
     fn drop(&mut self) {
         if let Some(mut file) = self.file.take() {
-            let _ = file.flush().unwrap();
+            // Never panic from `Drop`; a failed flush on teardown is ignored.
+            let _ = file.flush();
         }
     }
 }
 
-pub fn init_loggery(filename: impl AsRef<Path>) -> Result<()> {
-    let _ = LOGGER.lock().unwrap().init(filename)?;
+pub fn init_loggery(config: ConfigLogging) -> Result<(), LoggeryError> {
+    let mut logger = LOGGER.lock().unwrap();
+    logger.init(config)?;
+    if let Ok(value) = env::var("RUST_LOG") {
+        if let Some(level) = LogLevel::from_env_str(&value) {
+            logger.max_level = level;
+        }
+    }
     Ok(())
 }
 
+/// Set the maximum severity that will be written; messages below this
+/// threshold are silently dropped. `Todo` is always emitted.
+pub fn set_level(level: LogLevel) {
+    LOGGER.lock().unwrap().max_level = level;
+}
+
+/// Select the record serialization format (plain text or newline-delimited
+/// JSON).
+pub fn set_format(format: LogFormat) {
+    LOGGER.lock().unwrap().format = format;
+}
+
+/// Toggle the leading RFC 3339 UTC timestamp on each record. Off by default so
+/// callers that assert exact output can opt in explicitly.
+pub fn set_timestamp(enabled: bool) {
+    LOGGER.lock().unwrap().timestamp = enabled;
+}
+
+/// Run `f` with log records captured into an in-memory buffer instead of being
+/// written to the file or terminal, returning the collected lines. Capturing
+/// is thread-local, so concurrent tests do not interfere with one another.
+pub fn with_captured_logs<F: FnOnce()>(f: F) -> Vec<String> {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let previous = CAPTURE.with(|slot| slot.replace(Some(buffer.clone())));
+    f();
+    CAPTURE.with(|slot| *slot.borrow_mut() = previous);
+    Arc::try_unwrap(buffer)
+        .map(|inner| inner.into_inner().unwrap())
+        .unwrap_or_else(|arc| arc.lock().unwrap().clone())
+}
+
 #[macro_export]
 macro_rules! infoy {
     ($( $arg:tt )*) => {{
@@ -134,6 +508,62 @@ macro_rules! errory {
     }};
 }
 
+/// Build a `Vec<(String, StructuredValue)>` from a `key => value` /
+/// `name { .. }` field list. Keys may be identifiers or string literals (the
+/// latter allowing spaces). Internal helper for [`logy!`].
+#[macro_export]
+macro_rules! sfields {
+    (@acc $vec:ident,) => {};
+    (@acc $vec:ident, $key:ident => $val:expr $(, $($rest:tt)*)?) => {
+        $vec.push((
+            stringify!($key).to_string(),
+            $crate::StructuredValue::Leaf(format!("{}", $val)),
+        ));
+        $crate::sfields!(@acc $vec, $($($rest)*)?);
+    };
+    (@acc $vec:ident, $key:literal => $val:expr $(, $($rest:tt)*)?) => {
+        $vec.push((
+            $key.to_string(),
+            $crate::StructuredValue::Leaf(format!("{}", $val)),
+        ));
+        $crate::sfields!(@acc $vec, $($($rest)*)?);
+    };
+    (@acc $vec:ident, $name:ident { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        {
+            let mut nested = Vec::new();
+            $crate::sfields!(@acc nested, $($inner)*);
+            $vec.push((stringify!($name).to_string(), $crate::StructuredValue::Nested(nested)));
+        }
+        $crate::sfields!(@acc $vec, $($($rest)*)?);
+    };
+    (@acc $vec:ident, $name:literal { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        {
+            let mut nested = Vec::new();
+            $crate::sfields!(@acc nested, $($inner)*);
+            $vec.push(($name.to_string(), $crate::StructuredValue::Nested(nested)));
+        }
+        $crate::sfields!(@acc $vec, $($($rest)*)?);
+    };
+}
+
+/// Log a message with structured, nested key/value context, e.g.
+/// `logy!(Info, "done {}", n; count => n, meta { host => h })`.
+#[macro_export]
+macro_rules! logy {
+    ($level:ident, $fmt:literal $(, $arg:expr)* ; $($fields:tt)*) => {{
+        let message = format!($fmt $(, $arg)*);
+        let mut fields = Vec::new();
+        $crate::sfields!(@acc fields, $($fields)*);
+        let logger = LOGGER.lock().unwrap();
+        logger.log_structured($crate::LogLevel::$level, &message, fields)
+    }};
+    ($level:ident, $fmt:literal $(, $arg:expr)*) => {{
+        let message = format!($fmt $(, $arg)*);
+        let logger = LOGGER.lock().unwrap();
+        logger.log_structured($crate::LogLevel::$level, &message, Vec::new())
+    }};
+}
+
 #[macro_export]
 macro_rules! todoy {
     ($( $arg:tt )*) => {{
@@ -172,7 +602,10 @@ mod tests {
     fn setup(should_clear: bool) -> Result<PathBuf> {
         let mut project = find_project_dir().unwrap_or(PathBuf::new());
         project.push(".yash.log");
-        let _ = init_loggery(project.clone());
+        let _ = init_loggery(ConfigLogging::File {
+            path: project.clone(),
+            if_exists: IfExists::Append,
+        });
         if should_clear {
             clear_file(project.clone())?;
         }
@@ -233,6 +666,58 @@ mod tests {
         assert_eq!(a && b && c && d, true);
     }
 
+    #[test]
+    fn test_capture() {
+        let captured = with_captured_logs(|| {
+            infoy!("yash is testing {} {} {}!!!", 1, 2, 3).unwrap();
+        });
+        assert_eq!(captured, vec!["[INFO] yash is testing 1 2 3!!!".to_string()]);
+    }
+
+    #[test]
+    fn test_structured_nested() {
+        let captured = with_captured_logs(|| {
+            logy!(Info, "done {}", 7; count => 7, "peer host" => "db1",
+                  meta { region => "us", inner { depth => 3 } })
+            .unwrap();
+        });
+        let expected = "[INFO] done 7\n  count: 7\n  peer host: db1\n  meta:\n    region: us\n    inner:\n      depth: 3";
+        assert_eq!(captured, vec![expected.to_string()]);
+    }
+
+    #[test]
+    fn test_json_field_order() {
+        // Drive a dedicated `Logger` against its own file so global format
+        // state and other tests are untouched (parallel-safe).
+        let mut path = env::temp_dir();
+        path.push("yash-json-order.log");
+
+        let mut logger = Logger::new();
+        logger
+            .init(ConfigLogging::File {
+                path: path.clone(),
+                if_exists: IfExists::Truncate,
+            })
+            .unwrap();
+        logger.format = LogFormat::Json;
+
+        // Without a timestamp the record is byte-for-byte exact.
+        logger.log_content(LogLevel::Info, "hi 1").unwrap();
+        let line = fs::read_to_string(&path).unwrap();
+        assert_eq!(line, "{\"level\":\"info\",\"msg\":\"hi 1\"}\n");
+
+        // With a timestamp, `time` leads despite being alphabetically last.
+        clear_file(&path).unwrap();
+        logger.timestamp = true;
+        logger.log_content(LogLevel::Info, "hi 2").unwrap();
+        let line = fs::read_to_string(&path).unwrap();
+        assert!(line.starts_with("{\"time\":\""), "time must come first: {}", line);
+        let time_at = line.find("\"time\"").unwrap();
+        let level_at = line.find("\"level\"").unwrap();
+        let msg_at = line.find("\"msg\"").unwrap();
+        assert!(time_at < level_at && level_at < msg_at, "order wrong: {}", line);
+    }
+
     #[test]
     #[should_panic]
     fn test_todoy() {